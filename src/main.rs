@@ -1,52 +1,367 @@
-use std::{fs::File, str::FromStr};
+use std::{
+  f64::consts::LN_2,
+  fs::File,
+  io::Write,
+  path::Path,
+  str::FromStr,
+  sync::atomic::{AtomicU32, Ordering},
+};
 
+use clap::Parser;
+use indicatif::ProgressBar;
 use num::Complex;
+use rand::Rng;
+use rayon::prelude::*;
 
-use image::{png::PNGEncoder, ColorType};
+use image::{jpeg::JPEGEncoder, png::PNGEncoder, ColorType};
+
+/// Selects which iteration formula `escape_time` applies at each point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FractalKind {
+  Mandelbrot,
+  MandelbrotPower(u32),
+  BurningShip,
+  Tricorn,
+}
+
+impl FromStr for FractalKind {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Some(power) = s.strip_prefix("power") {
+      return u32::from_str(power)
+        .map(FractalKind::MandelbrotPower)
+        .map_err(|e| format!("error parsing power from '{}': {}", s, e));
+    }
+
+    match s {
+      "mandelbrot" => Ok(FractalKind::Mandelbrot),
+      "burning-ship" => Ok(FractalKind::BurningShip),
+      "tricorn" => Ok(FractalKind::Tricorn),
+      _ => Err(format!("unrecognized fractal kind: '{}'", s)),
+    }
+  }
+}
+
+/// Selects how escape counts are mapped to output bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Palette {
+  Grayscale,
+  Hsv,
+}
+
+impl FromStr for Palette {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "grayscale" | "gray" => Ok(Palette::Grayscale),
+      "hsv" => Ok(Palette::Hsv),
+      _ => Err(format!("unrecognized palette: '{}'", s)),
+    }
+  }
+}
+
+/// Selects which traversal `main` uses to produce the output image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+  Mandelbrot,
+  Buddhabrot,
+}
+
+impl FromStr for Mode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "mandelbrot" => Ok(Mode::Mandelbrot),
+      "buddhabrot" => Ok(Mode::Buddhabrot),
+      _ => Err(format!("unrecognized mode: '{}'", s)),
+    }
+  }
+}
+
+/// Render Mandelbrot-family fractals and Buddhabrot density maps.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+  /// Which traversal to render: `mandelbrot` or `buddhabrot`.
+  #[arg(long, default_value = "mandelbrot", value_parser = Mode::from_str)]
+  mode: Mode,
+
+  /// Output image file.
+  #[arg(long, short)]
+  output: String,
+
+  /// Image dimensions, as `WIDTHxHEIGHT`.
+  #[arg(long, value_parser = parse_size)]
+  size: (usize, usize),
+
+  /// The point on the complex plane at the upper-left corner of the image.
+  #[arg(long = "upper-left", allow_hyphen_values = true, value_parser = parse_point)]
+  upper_left: Complex<f64>,
+
+  /// The point on the complex plane at the lower-right corner of the image.
+  #[arg(long = "lower-right", allow_hyphen_values = true, value_parser = parse_point)]
+  lower_right: Complex<f64>,
+
+  /// Iteration limit used to decide whether a point has escaped.
+  #[arg(long, default_value_t = 255)]
+  iterations: u32,
+
+  /// Size of the Rayon thread pool. Defaults to Rayon's own heuristic.
+  #[arg(long)]
+  threads: Option<usize>,
+
+  /// Iteration formula to use, for `--mode mandelbrot`.
+  #[arg(long, default_value = "mandelbrot", value_parser = FractalKind::from_str)]
+  fractal: FractalKind,
+
+  /// Output coloring, for `--mode mandelbrot`.
+  #[arg(long, default_value = "grayscale", value_parser = Palette::from_str)]
+  color: Palette,
+
+  /// Number of orbits to sample, required for `--mode buddhabrot`.
+  #[arg(long)]
+  samples: Option<u64>,
+
+  /// Show a progress bar while rendering.
+  #[arg(long)]
+  progress: bool,
+}
+
+#[test]
+fn test_args_accepts_space_separated_negative_coordinates() {
+  let args = Args::try_parse_from([
+    "mandelbrot",
+    "--output",
+    "out.png",
+    "--size",
+    "100x100",
+    "--upper-left",
+    "-1.20,0.35",
+    "--lower-right",
+    "-1,0.20",
+  ])
+  .unwrap();
+
+  assert_eq!(
+    args.upper_left,
+    Complex {
+      re: -1.20,
+      im: 0.35
+    }
+  );
+  assert_eq!(
+    args.lower_right,
+    Complex {
+      re: -1.0,
+      im: 0.20
+    }
+  );
+}
 
 fn main() {
-  let args: Vec<String> = std::env::args().collect();
+  let args = Args::parse();
+
+  if let Some(threads) = args.threads {
+    rayon::ThreadPoolBuilder::new()
+      .num_threads(threads)
+      .build_global()
+      .expect("error configuring thread pool");
+  }
+
+  let result = match args.mode {
+    Mode::Mandelbrot => {
+      let bar = args.progress.then(|| ProgressBar::new(args.size.1 as u64));
+
+      match args.color {
+        Palette::Grayscale => {
+          let mut pixels = vec![0; args.size.0 * args.size.1];
+          let viewport = Viewport {
+            bounds: args.size,
+            upper_left: args.upper_left,
+            lower_right: args.lower_right,
+          };
+
+          render_parallel(
+            args.fractal,
+            &mut pixels,
+            viewport,
+            args.iterations,
+            bar.as_ref(),
+          );
+
+          if let Some(bar) = &bar {
+            bar.finish();
+          }
+
+          write_image(&args.output, &pixels, args.size, ColorType::Gray(8))
+        }
+        _ => {
+          let mut pixels = vec![0; args.size.0 * args.size.1 * 3];
+          let viewport = Viewport {
+            bounds: args.size,
+            upper_left: args.upper_left,
+            lower_right: args.lower_right,
+          };
 
-  if args.len() != 6 {
-    eprintln!("Usage: mandelbrot FILE PIXELS UPPERLEFT LOWERRIGHT THREADS");
-    eprintln!(
-      "Example: {} mandel.png 1000x750 -1.20,0.35 -1,0.20 8",
-      args[0]
-    );
+          render_color_parallel(
+            args.fractal,
+            args.color,
+            &mut pixels,
+            viewport,
+            args.iterations,
+            bar.as_ref(),
+          );
 
+          if let Some(bar) = &bar {
+            bar.finish();
+          }
+
+          write_image(&args.output, &pixels, args.size, ColorType::RGB(8))
+        }
+      }
+    }
+    Mode::Buddhabrot => {
+      let samples = args.samples.expect("--samples is required for --mode buddhabrot");
+      let bar = args
+        .progress
+        .then(|| ProgressBar::new(rayon::current_num_threads() as u64));
+
+      let mut pixels = vec![0; args.size.0 * args.size.1];
+      let viewport = Viewport {
+        bounds: args.size,
+        upper_left: args.upper_left,
+        lower_right: args.lower_right,
+      };
+
+      render_buddhabrot(&mut pixels, viewport, samples, args.iterations, bar.as_ref());
+
+      if let Some(bar) = &bar {
+        bar.finish();
+      }
+
+      write_image(&args.output, &pixels, args.size, ColorType::Gray(8))
+    }
+  };
+
+  if let Err(error) = result {
+    eprintln!("error writing image file: {}", error);
     std::process::exit(1);
   }
+}
 
-  let bounds = parse_pair(&args[2], 'x').expect("error parsing image dimensions");
-  let upper_left = parse_complex(&args[3]).expect("error parsing upper left value");
-  let lower_right = parse_complex(&args[4]).expect("error parsing lower right value");
+/// Parse a `WIDTHxHEIGHT` pixel size, like `"1000x750"`, for use as a clap
+/// value parser.
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+  parse_pair(s, 'x').ok_or_else(|| format!("invalid size '{}', expected WIDTHxHEIGHT", s))
+}
 
-  let mut pixels = vec![0; bounds.0 * bounds.1];
+/// Parse a `RE,IM` complex point, like `"-1.20,0.35"`, for use as a clap
+/// value parser.
+fn parse_point(s: &str) -> Result<Complex<f64>, String> {
+  parse_complex(s).ok_or_else(|| format!("invalid point '{}', expected RE,IM", s))
+}
 
-  let threads = usize::from_str(&args[5]).expect("error parsing number of threads");
-  let rows_per_band = bounds.1 / threads + 1;
+/// Render the `kind` fractal's set into `pixels` in parallel, dispatching one
+/// Rayon task per row of the image via `par_chunks_mut`.
+///
+/// If `progress` is given, it is ticked once per completed row.
+fn render_parallel(
+  kind: FractalKind,
+  pixels: &mut [u8],
+  viewport: Viewport,
+  limit: u32,
+  progress: Option<&ProgressBar>,
+) {
+  let bounds = viewport.bounds;
 
-  {
-    let bands: Vec<&mut [u8]> = pixels.chunks_mut(rows_per_band * bounds.0).collect();
-    crossbeam::scope(|spawner| {
-      for (i, band) in bands.into_iter().enumerate() {
-        let top = rows_per_band * i;
-        let height = band.len() / bounds.0;
+  pixels
+    .par_chunks_mut(bounds.0)
+    .enumerate()
+    .for_each(|(row, pixel_row)| {
+      let row_upper_left =
+        pixel_to_point(bounds, (0, row), viewport.upper_left, viewport.lower_right);
+      let row_lower_right = pixel_to_point(
+        bounds,
+        (bounds.0, row + 1),
+        viewport.upper_left,
+        viewport.lower_right,
+      );
 
-        let band_bounds = (bounds.0, height);
-        let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-        let band_lower_right =
-          pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
+      render(
+        kind,
+        pixel_row,
+        Viewport {
+          bounds: (bounds.0, 1),
+          upper_left: row_upper_left,
+          lower_right: row_lower_right,
+        },
+        limit,
+      );
 
-        spawner.spawn(move |_| {
-          render(band, band_bounds, band_upper_left, band_lower_right);
-        });
+      if let Some(bar) = progress {
+        bar.inc(1);
       }
-    })
-    .expect("failed to compute bands");
-  }
+    });
+}
+
+/// Render the `kind` fractal's set into an RGB `pixels` buffer in parallel,
+/// dispatching one Rayon task per row of the image via `par_chunks_mut`.
+///
+/// If `progress` is given, it is ticked once per completed row.
+fn render_color_parallel(
+  kind: FractalKind,
+  palette: Palette,
+  pixels: &mut [u8],
+  viewport: Viewport,
+  limit: u32,
+  progress: Option<&ProgressBar>,
+) {
+  let bounds = viewport.bounds;
+
+  pixels
+    .par_chunks_mut(bounds.0 * 3)
+    .enumerate()
+    .for_each(|(row, pixel_row)| {
+      let row_upper_left =
+        pixel_to_point(bounds, (0, row), viewport.upper_left, viewport.lower_right);
+      let row_lower_right = pixel_to_point(
+        bounds,
+        (bounds.0, row + 1),
+        viewport.upper_left,
+        viewport.lower_right,
+      );
+
+      render_color(
+        kind,
+        palette,
+        pixel_row,
+        Viewport {
+          bounds: (bounds.0, 1),
+          upper_left: row_upper_left,
+          lower_right: row_lower_right,
+        },
+        limit,
+      );
+
+      if let Some(bar) = progress {
+        bar.inc(1);
+      }
+    });
+}
 
-  write_image(&args[1], &pixels, bounds).expect("error writing PNG file");
+/// The complex-plane region a render covers and the pixel dimensions of the
+/// buffer it's rendered into. Bundled because every render function needs
+/// all three together, and passing them separately trips
+/// `clippy::too_many_arguments` once a few more knobs (palette, progress)
+/// join the list.
+#[derive(Clone, Copy, Debug)]
+struct Viewport {
+  bounds: (usize, usize),
+  upper_left: Complex<f64>,
+  lower_right: Complex<f64>,
 }
 
 /// Parse the string `target` as a coordinate pair, like `"400x600"` or `"1.0,0.5"`.
@@ -104,26 +419,87 @@ fn test_parse_complex() {
   assert_eq!(parse_complex(",1.25"), None);
 }
 
-/// Try to determine if `target` is in the Mandelbrot set, using at most `limit`
-/// iterations to decide.
+#[test]
+fn test_fractal_kind_from_str() {
+  assert_eq!(
+    FractalKind::from_str("mandelbrot"),
+    Ok(FractalKind::Mandelbrot)
+  );
+  assert_eq!(
+    FractalKind::from_str("burning-ship"),
+    Ok(FractalKind::BurningShip)
+  );
+  assert_eq!(FractalKind::from_str("tricorn"), Ok(FractalKind::Tricorn));
+  assert_eq!(
+    FractalKind::from_str("power3"),
+    Ok(FractalKind::MandelbrotPower(3))
+  );
+  assert!(FractalKind::from_str("nonsense").is_err());
+}
+
+/// Advance `z` by one iteration of the formula for `kind`, given the point `c`
+/// the orbit is centered on.
+fn step(kind: FractalKind, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+  match kind {
+    FractalKind::Mandelbrot => z * z + c,
+    FractalKind::MandelbrotPower(n) => z.powu(n) + c,
+    FractalKind::BurningShip => {
+      let folded = Complex {
+        re: z.re.abs(),
+        im: z.im.abs(),
+      };
+
+      folded * folded + c
+    }
+    FractalKind::Tricorn => {
+      let conjugate = z.conj();
+
+      conjugate * conjugate + c
+    }
+  }
+}
+
+/// Try to determine if `target` is in the `kind` fractal's set, using at most
+/// `limit` iterations to decide.
 ///
 /// If `target` is not a member, return `Some(i)`, where `i` is the number of
 /// iterations it took for `target` to leave the circle of radius two centered on the
 /// origin. If `target` seems to be a member (more precisely, if we reached the
 /// iteration limit without being able to prove that `target` is not a member),
 /// return `None`.
-fn escape_time(target: Complex<f64>, limit: u32) -> Option<u32> {
+fn escape_time(kind: FractalKind, target: Complex<f64>, limit: u32) -> Option<u32> {
+  escape_time_with_z(kind, target, limit).map(|(count, _)| count)
+}
+
+/// Like `escape_time`, but also returns the escaped value of `z`, which the
+/// caller needs to compute a smoothed (continuous) escape count.
+fn escape_time_with_z(
+  kind: FractalKind,
+  target: Complex<f64>,
+  limit: u32,
+) -> Option<(u32, Complex<f64>)> {
   let mut accumulator = Complex { re: 0.0, im: 0.0 };
   for i in 0..limit {
-    accumulator = accumulator * accumulator + target;
+    accumulator = step(kind, accumulator, target);
     if accumulator.norm_sqr() > 4.0 {
-      return Some(i);
+      return Some((i, accumulator));
     }
   }
 
   None
 }
 
+/// Compute the normalized (smoothed) escape count for a point that escaped at
+/// iteration `count` with final value `z`.
+///
+/// Coloring by the raw integer `count` produces visible banding; this
+/// continuous count varies smoothly between iterations instead.
+fn smoothed_escape_count(count: u32, z: Complex<f64>) -> f64 {
+  let magnitude = z.norm_sqr().sqrt();
+
+  count as f64 + 1.0 - magnitude.ln().ln() / LN_2
+}
+
 /// Given the row and column of a pixel in the output image, return the
 /// corresponding point on the complex plane.
 ///
@@ -161,49 +537,336 @@ fn test_pixel_to_point() {
   );
 }
 
-/// Render a rectangle of the Mandelbrot set into a buffer of pixels.
-///
-/// The `bounds` argument gives the width and height of the buffer `pixels`,
-/// which holds one grayscale pixel per byte. The `upper_left` and `lower_right`
-/// arguments specify points on the complex plane corresponding to the upper-
-/// left and lower-right corners of the pixel buffer.
-fn render(
-  pixels: &mut [u8],
+/// Given a point on the complex plane, return the pixel it falls into, or
+/// `None` if the point lies outside the viewport described by `upper_left`
+/// and `lower_right`. This is the inverse of `pixel_to_point`.
+fn point_to_pixel(
   bounds: (usize, usize),
   upper_left: Complex<f64>,
   lower_right: Complex<f64>,
-) {
+  point: Complex<f64>,
+) -> Option<(usize, usize)> {
+  let (width, height) = (
+    lower_right.re - upper_left.re,
+    upper_left.im - lower_right.im,
+  );
+
+  let column = (point.re - upper_left.re) / width * bounds.0 as f64;
+  let row = (upper_left.im - point.im) / height * bounds.1 as f64;
+
+  if column < 0.0 || row < 0.0 || column >= bounds.0 as f64 || row >= bounds.1 as f64 {
+    return None;
+  }
+
+  Some((column as usize, row as usize))
+}
+
+#[test]
+fn test_point_to_pixel() {
+  assert_eq!(
+    point_to_pixel(
+      (100, 100),
+      Complex { re: -1.0, im: 1.0 },
+      Complex { re: 1.0, im: -1.0 },
+      Complex { re: -0.5, im: -0.5 }
+    ),
+    Some((25, 75))
+  );
+
+  assert_eq!(
+    point_to_pixel(
+      (100, 100),
+      Complex { re: -1.0, im: 1.0 },
+      Complex { re: 1.0, im: -1.0 },
+      Complex { re: 5.0, im: 5.0 }
+    ),
+    None
+  );
+}
+
+/// Render a rectangle of the `kind` fractal's set into a buffer of pixels,
+/// using at most `limit` iterations to decide whether each point escapes.
+///
+/// `viewport.bounds` gives the width and height of the buffer `pixels`,
+/// which holds one grayscale pixel per byte; `viewport.upper_left` and
+/// `viewport.lower_right` are the points on the complex plane corresponding
+/// to the pixel buffer's corners.
+fn render(kind: FractalKind, pixels: &mut [u8], viewport: Viewport, limit: u32) {
+  let bounds = viewport.bounds;
   assert!(pixels.len() == bounds.0 * bounds.1);
 
   for row in 0..bounds.1 {
     for column in 0..bounds.0 {
-      let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+      let point = pixel_to_point(bounds, (column, row), viewport.upper_left, viewport.lower_right);
 
-      pixels[row * bounds.0 + column] = match escape_time(point, 255) {
+      pixels[row * bounds.0 + column] = match escape_time(kind, point, limit) {
         None => 0,
-        Some(count) => 255 - count as u8,
+        // Widen to u64 before multiplying: `count * 255` overflows u32 once
+        // `limit` (and thus `count`) exceeds ~16.8M.
+        Some(count) => 255 - (count as u64 * 255 / limit as u64) as u8,
+      }
+    }
+  }
+}
+
+/// Render a rectangle of the `kind` fractal's set into an RGB buffer, coloring
+/// each pixel by its smoothed escape count through `palette`, using at most
+/// `limit` iterations to decide whether each point escapes.
+///
+/// `viewport.bounds` gives the width and height of the buffer `pixels`, which
+/// holds three RGB bytes per pixel; `viewport.upper_left` and
+/// `viewport.lower_right` are the points on the complex plane corresponding
+/// to the pixel buffer's corners.
+fn render_color(
+  kind: FractalKind,
+  palette: Palette,
+  pixels: &mut [u8],
+  viewport: Viewport,
+  limit: u32,
+) {
+  let bounds = viewport.bounds;
+  assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+  for row in 0..bounds.1 {
+    for column in 0..bounds.0 {
+      let point = pixel_to_point(bounds, (column, row), viewport.upper_left, viewport.lower_right);
+
+      let rgb = match escape_time_with_z(kind, point, limit) {
+        None => [0, 0, 0],
+        Some((count, z)) => {
+          let mu = smoothed_escape_count(count, z);
+          color(palette, mu / limit as f64)
+        }
+      };
+
+      let offset = (row * bounds.0 + column) * 3;
+      pixels[offset..offset + 3].copy_from_slice(&rgb);
+    }
+  }
+}
+
+/// Map a normalized escape ratio (typically in `[0, 1]`, but smoothing can
+/// push it slightly outside that range) to an RGB triple using `palette`.
+fn color(palette: Palette, ratio: f64) -> [u8; 3] {
+  let ratio = ratio.clamp(0.0, 1.0);
+
+  match palette {
+    Palette::Grayscale => {
+      let value = (ratio * 255.0).round() as u8;
+      [value, value, value]
+    }
+    Palette::Hsv => hsv_to_rgb(ratio * 360.0, 1.0, 1.0),
+  }
+}
+
+/// Convert an HSV color (`hue` in degrees, `saturation` and `value` in
+/// `[0, 1]`) to an RGB triple.
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [u8; 3] {
+  let c = value * saturation;
+  let x = c * (1.0 - (((hue / 60.0) % 2.0) - 1.0).abs());
+  let m = value - c;
+
+  let (r, g, b) = match hue as u32 {
+    0..=59 => (c, x, 0.0),
+    60..=119 => (x, c, 0.0),
+    120..=179 => (0.0, c, x),
+    180..=239 => (0.0, x, c),
+    240..=299 => (x, 0.0, c),
+    _ => (c, 0.0, x),
+  };
+
+  [
+    ((r + m) * 255.0).round() as u8,
+    ((g + m) * 255.0).round() as u8,
+    ((b + m) * 255.0).round() as u8,
+  ]
+}
+
+/// Wide, fixed region of the complex plane that orbits are sampled from,
+/// independent of the output viewport. A true Buddhabrot needs orbits of `c`
+/// points that originate outside the frame but pass through it; restricting
+/// sampling to the viewport would starve zoomed-in renders of density.
+const SAMPLE_RE: (f64, f64) = (-2.5, 1.5);
+const SAMPLE_IM: (f64, f64) = (-1.25, 1.25);
+
+/// Render a Buddhabrot accumulation of orbit density into a grayscale buffer.
+///
+/// Unlike `render`, this does not evaluate one point per pixel. Instead it
+/// samples `samples` random points `c` from `SAMPLE_RE`/`SAMPLE_IM` and
+/// iterates `z = z*z + c`; orbits that escape within `limit` iterations are
+/// replayed, and every intermediate `z` that falls inside `viewport` bumps
+/// the count at its pixel. Orbits that never escape are discarded. Counts
+/// accumulate into an atomic grid shared across Rayon's worker threads, then
+/// the busiest pixel is normalized to 255.
+///
+/// If `progress` is given, it is ticked once per worker that finishes its
+/// share of samples.
+fn render_buddhabrot(
+  pixels: &mut [u8],
+  viewport: Viewport,
+  samples: u64,
+  limit: u32,
+  progress: Option<&ProgressBar>,
+) {
+  let bounds = viewport.bounds;
+  assert!(pixels.len() == bounds.0 * bounds.1);
+
+  let counts: Vec<AtomicU32> = (0..bounds.0 * bounds.1).map(|_| AtomicU32::new(0)).collect();
+
+  let workers = rayon::current_num_threads() as u64;
+  let samples_per_worker = samples / workers;
+  let remainder = samples % workers;
+
+  (0..workers).into_par_iter().for_each(|worker| {
+    let share = samples_per_worker + u64::from(worker < remainder);
+    accumulate_orbits(&counts, viewport, share, limit);
+
+    if let Some(bar) = progress {
+      bar.inc(1);
+    }
+  });
+
+  let max_count = counts
+    .iter()
+    .map(|count| count.load(Ordering::Relaxed))
+    .max()
+    .unwrap_or(0)
+    .max(1);
+
+  for (pixel, count) in pixels.iter_mut().zip(counts.iter()) {
+    let count = count.load(Ordering::Relaxed);
+    *pixel = (count as f64 * 255.0 / max_count as f64).round() as u8;
+  }
+}
+
+/// Sample `samples` random points `c` from `SAMPLE_RE`/`SAMPLE_IM`, replay
+/// the orbits of those that escape within `limit` iterations, and bump
+/// `counts` at every pixel of `viewport` an intermediate `z` lands in.
+fn accumulate_orbits(counts: &[AtomicU32], viewport: Viewport, samples: u64, limit: u32) {
+  let bounds = viewport.bounds;
+  let mut rng = rand::thread_rng();
+  let mut orbit = Vec::with_capacity(limit as usize);
+
+  for _ in 0..samples {
+    let c = Complex {
+      re: rng.gen_range(SAMPLE_RE.0..SAMPLE_RE.1),
+      im: rng.gen_range(SAMPLE_IM.0..SAMPLE_IM.1),
+    };
+
+    orbit.clear();
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    let mut escaped = false;
+
+    for _ in 0..limit {
+      z = z * z + c;
+      orbit.push(z);
+      if z.norm_sqr() > 4.0 {
+        escaped = true;
+        break;
+      }
+    }
+
+    if !escaped {
+      continue;
+    }
+
+    for z in &orbit {
+      if let Some((column, row)) =
+        point_to_pixel(bounds, viewport.upper_left, viewport.lower_right, *z)
+      {
+        counts[row * bounds.0 + column].fetch_add(1, Ordering::Relaxed);
       }
     }
   }
 }
 
 /// Write the buffer `pixels`, whose dimensions are given by `bounds`, to the
-/// file named `filename`.
+/// file named `filename`, picking an encoder from `filename`'s extension.
+///
+/// `.png` writes a PNG, `.jpg`/`.jpeg` writes a JPEG, and `.ppm`/`.pgm` writes
+/// a raw PNM (`P6` for RGB, `P5` for grayscale), which needs no codec and is
+/// trivial to pipe between tools. Any other extension is an error.
 fn write_image(
   filename: &str,
   pixels: &[u8],
   bounds: (usize, usize),
+  color_type: ColorType,
+) -> Result<(), std::io::Error> {
+  let extension = Path::new(filename)
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .map(|ext| ext.to_ascii_lowercase());
+
+  match extension.as_deref() {
+    Some("png") => write_png(filename, pixels, bounds, color_type),
+    Some("jpg") | Some("jpeg") => write_jpeg(filename, pixels, bounds, color_type),
+    Some("ppm") | Some("pgm") => write_pnm(filename, pixels, bounds, color_type),
+    _ => Err(std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!(
+        "cannot determine image format for '{}' (expected a .png, .jpg/.jpeg, or .ppm/.pgm extension)",
+        filename
+      ),
+    )),
+  }
+}
+
+/// Write `pixels` as a PNG to `filename`.
+fn write_png(
+  filename: &str,
+  pixels: &[u8],
+  bounds: (usize, usize),
+  color_type: ColorType,
 ) -> Result<(), std::io::Error> {
   let output = File::create(filename)?;
 
   let encoder = PNGEncoder::new(output);
 
-  encoder.encode(
-    &pixels,
-    bounds.0 as u32,
-    bounds.1 as u32,
-    ColorType::Gray(8),
-  )?;
+  encoder.encode(pixels, bounds.0 as u32, bounds.1 as u32, color_type)?;
+
+  Ok(())
+}
+
+/// Write `pixels` as a JPEG to `filename`.
+fn write_jpeg(
+  filename: &str,
+  pixels: &[u8],
+  bounds: (usize, usize),
+  color_type: ColorType,
+) -> Result<(), std::io::Error> {
+  let mut output = File::create(filename)?;
+
+  let mut encoder = JPEGEncoder::new(&mut output);
+
+  encoder.encode(pixels, bounds.0 as u32, bounds.1 as u32, color_type)?;
+
+  Ok(())
+}
+
+/// Write `pixels` as a raw PNM to `filename`: `P5` for 8-bit grayscale, `P6`
+/// for 8-bit RGB, each with the ASCII header `<magic>\n<w> <h>\n255\n`
+/// followed by the raw pixel bytes.
+fn write_pnm(
+  filename: &str,
+  pixels: &[u8],
+  bounds: (usize, usize),
+  color_type: ColorType,
+) -> Result<(), std::io::Error> {
+  let magic = match color_type {
+    ColorType::Gray(8) => "P5",
+    ColorType::RGB(8) => "P6",
+    _ => {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "PNM output only supports 8-bit grayscale or RGB",
+      ))
+    }
+  };
+
+  let mut output = File::create(filename)?;
+
+  write!(output, "{}\n{} {}\n255\n", magic, bounds.0, bounds.1)?;
+  output.write_all(pixels)?;
 
   Ok(())
 }